@@ -3,14 +3,29 @@ use actix_web::{
     http::header::{HeaderName, HeaderValue},
     web,
 };
+use async_compression::tokio::bufread::{BrotliEncoder, DeflateEncoder, GzipEncoder};
 use clap::Parser;
+use futures_util::{Stream, TryStreamExt, stream};
+use lru::LruCache;
 use once_cell::sync::Lazy;
 use reqwest::{Client, Method};
-use std::{collections::HashMap, str::FromStr, time::Duration};
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    str::FromStr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio_util::io::{ReaderStream, StreamReader};
 use url::Url;
 
 // 配置结构体，支持命令行参数和环境变量
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
 struct Config {
     /// 服务器监听地址
@@ -36,34 +51,319 @@ struct Config {
     /// 连接超时时间 (秒)
     #[arg(long, default_value = "10", env = "CONNECT_TIMEOUT")]
     connect_timeout: u64,
+
+    /// 是否对可压缩的响应体进行透明压缩 (br/gzip/deflate)
+    #[arg(long, default_value = "true", env = "PROXY_COMPRESSION")]
+    compression: bool,
+
+    /// Unix 域套接字路径 - 设置后额外在该套接字上监听，默认与 TCP 绑定共存
+    #[arg(long, env = "PROXY_UDS")]
+    uds: Option<String>,
+
+    /// Unix 域套接字文件权限 (八进制)
+    #[arg(long, default_value = "660", env = "PROXY_UDS_PERMS")]
+    uds_perms: String,
+
+    /// 禁用 TCP 监听，仅通过 --uds 提供服务 (需要同时设置 --uds)
+    #[arg(long, default_value = "false", env = "PROXY_DISABLE_TCP")]
+    disable_tcp: bool,
+
+    /// 额外的 API 映射配置文件路径 (TOML 或 JSON，按扩展名判断)，条目会覆盖内置映射
+    #[arg(long, env = "PROXY_MAPPING_FILE")]
+    mapping_file: Option<String>,
+
+    /// 请求 URI 路径部分的最大长度 (字节)
+    #[arg(long, default_value = "4096", env = "MAX_URI_LEN")]
+    max_uri_len: usize,
+
+    /// 请求查询字符串的最大长度 (字节)
+    #[arg(long, default_value = "8192", env = "MAX_QUERY_LEN")]
+    max_query_len: usize,
+
+    /// 结构化访问日志文件路径 - 设置后按 JSON Lines 格式记录每一次代理请求
+    #[arg(long, env = "PROXY_ACCESS_LOG")]
+    access_log: Option<String>,
+
+    /// 访问日志单个文件的滚动阈值 (字节)
+    #[arg(long, default_value = "10485760", env = "PROXY_ACCESS_LOG_MAX_BYTES")]
+    access_log_max_bytes: u64,
+
+    /// GET/HEAD 响应缓存的默认 TTL (秒)，当上游未指定 Cache-Control/Expires 时使用；0 表示禁用缓存
+    #[arg(long, default_value = "60", env = "PROXY_CACHE_TTL")]
+    cache_ttl: u64,
+
+    /// 单条缓存记录允许的最大响应体大小 (字节)
+    #[arg(long, default_value = "1048576", env = "PROXY_CACHE_MAX_ENTRY_BYTES")]
+    cache_max_entry_bytes: usize,
+
+    /// 缓存总字节数预算，超出后按 LRU 淘汰最久未使用的记录
+    #[arg(long, default_value = "67108864", env = "PROXY_CACHE_MAX_TOTAL_BYTES")]
+    cache_max_total_bytes: usize,
 }
 
-// API 映射配置 - 使用 HashMap 提高查找性能
-static API_MAPPING: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
-    let mut map = HashMap::new();
-    map.insert("/anthropic", "https://api.anthropic.com");
-    map.insert("/claude", "https://api.anthropic.com");
-    map.insert("/cerebras", "https://api.cerebras.ai");
-    map.insert("/cohere", "https://api.cohere.ai");
-    map.insert("/discord", "https://discord.com/api");
-    map.insert("/fireworks", "https://api.fireworks.ai");
-    map.insert("/gemini", "https://generativelanguage.googleapis.com");
-    map.insert("/groq", "https://api.groq.com/openai");
-    map.insert("/huggingface", "https://api-inference.huggingface.co");
-    map.insert("/meta", "https://www.meta.ai/api");
-    map.insert("/novita", "https://api.novita.ai");
-    map.insert("/nvidia", "https://integrate.api.nvidia.com");
-    map.insert("/oaipro", "https://api.oaipro.com");
-    map.insert("/openai", "https://api.openai.com");
-    map.insert("/openrouter", "https://openrouter.ai/api");
-    map.insert("/portkey", "https://api.portkey.ai");
-    map.insert("/reka", "https://api.reka.ai");
-    map.insert("/telegram", "https://api.telegram.org");
-    map.insert("/together", "https://api.together.xyz");
-    map.insert("/xai", "https://api.x.ai");
-    map.insert("/github", "https://api.github.com"); // 额外保留
-    map
-});
+// prefix -> 上游 base URL 的映射表
+type ApiMapping = HashMap<String, Url>;
+
+// 内置的 API 映射配置 - 开箱即用的常见上游地址，可被 --mapping-file 中的同名前缀覆盖
+fn default_api_mapping() -> ApiMapping {
+    let defaults: &[(&str, &str)] = &[
+        ("/anthropic", "https://api.anthropic.com"),
+        ("/claude", "https://api.anthropic.com"),
+        ("/cerebras", "https://api.cerebras.ai"),
+        ("/cohere", "https://api.cohere.ai"),
+        ("/discord", "https://discord.com/api"),
+        ("/fireworks", "https://api.fireworks.ai"),
+        ("/gemini", "https://generativelanguage.googleapis.com"),
+        ("/groq", "https://api.groq.com/openai"),
+        ("/huggingface", "https://api-inference.huggingface.co"),
+        ("/meta", "https://www.meta.ai/api"),
+        ("/novita", "https://api.novita.ai"),
+        ("/nvidia", "https://integrate.api.nvidia.com"),
+        ("/oaipro", "https://api.oaipro.com"),
+        ("/openai", "https://api.openai.com"),
+        ("/openrouter", "https://openrouter.ai/api"),
+        ("/portkey", "https://api.portkey.ai"),
+        ("/reka", "https://api.reka.ai"),
+        ("/telegram", "https://api.telegram.org"),
+        ("/together", "https://api.together.xyz"),
+        ("/xai", "https://api.x.ai"),
+        ("/github", "https://api.github.com"), // 额外保留
+    ];
+
+    defaults
+        .iter()
+        .map(|(prefix, base_url)| {
+            (
+                (*prefix).to_string(),
+                Url::parse(base_url).expect("built-in API mapping URL must be valid"),
+            )
+        })
+        .collect()
+}
+
+// 加载 API 映射 - 以内置映射为基础，合并 --mapping-file 中的条目（文件条目优先）
+fn load_api_mapping(config: &Config) -> std::io::Result<ApiMapping> {
+    let mut mapping = default_api_mapping();
+
+    let Some(path) = config.mapping_file.as_deref() else {
+        return Ok(mapping);
+    };
+
+    let content = std::fs::read_to_string(path)?;
+    let overrides: HashMap<String, String> = if path.ends_with(".json") {
+        serde_json::from_str(&content)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?
+    } else {
+        toml::from_str(&content)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?
+    };
+
+    for (prefix, base_url) in overrides {
+        let url = Url::parse(&base_url).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("invalid base URL for prefix '{}': {}", prefix, e),
+            )
+        })?;
+        let prefix = if prefix.starts_with('/') {
+            prefix
+        } else {
+            format!("/{}", prefix)
+        };
+        mapping.insert(prefix, url);
+    }
+
+    Ok(mapping)
+}
+
+// 单条访问日志记录 - 以 JSON Lines 格式写入访问日志文件
+#[derive(serde::Serialize)]
+struct AccessLogRecord {
+    timestamp: String,
+    client_ip: String,
+    method: String,
+    prefix: String,
+    target_url: String,
+    status: u16,
+    bytes: u64,
+    latency_ms: u128,
+}
+
+// 结构化访问日志 - 按字节大小阈值滚动文件
+struct AccessLogger {
+    path: String,
+    max_bytes: u64,
+    file: std::sync::Mutex<std::fs::File>,
+}
+
+impl AccessLogger {
+    fn new(path: &str, max_bytes: u64) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            path: path.to_string(),
+            max_bytes,
+            file: std::sync::Mutex::new(file),
+        })
+    }
+
+    // 当前文件超过阈值时，滚动为 `<path>.1` 并重新打开一个空文件
+    fn rotate_if_needed(&self, file: &mut std::fs::File) -> std::io::Result<()> {
+        if file.metadata()?.len() < self.max_bytes {
+            return Ok(());
+        }
+
+        let rotated_path = format!("{}.1", self.path);
+        std::fs::rename(&self.path, &rotated_path)?;
+        *file = std::fs::OpenOptions::new().create(true).append(true).open(&self.path)?;
+        Ok(())
+    }
+
+    fn log(&self, record: &AccessLogRecord) {
+        let line = match serde_json::to_string(record) {
+            Ok(line) => line,
+            Err(e) => {
+                log::warn!("Failed to serialize access log record: {}", e);
+                return;
+            }
+        };
+
+        let mut file = match self.file.lock() {
+            Ok(file) => file,
+            Err(e) => {
+                log::warn!("Access log mutex poisoned: {}", e);
+                return;
+            }
+        };
+
+        if let Err(e) = self.rotate_if_needed(&mut file) {
+            log::warn!("Failed to rotate access log {}: {}", self.path, e);
+        }
+
+        use std::io::Write;
+        if let Err(e) = writeln!(file, "{}", line) {
+            log::warn!("Failed to write access log {}: {}", self.path, e);
+        }
+    }
+}
+
+// 缓存键 - 区分方法、目标 URL 以及会影响响应内容或鉴权范围的请求头
+// Authorization/x-api-key/x-goog-api-key 均须入键，否则持有不同密钥的客户端会读到彼此的缓存响应
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    method: String,
+    url: String,
+    accept: Option<String>,
+    authorization: Option<String>,
+    api_key: Option<String>,
+    goog_api_key: Option<String>,
+}
+
+// 缓存的响应内容 - 足以在命中时重建一个完整的 HttpResponse
+#[derive(Debug, Clone)]
+struct CachedResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: web::Bytes,
+    expires_at: Instant,
+}
+
+// GET/HEAD 响应缓存 - 基于 LRU 淘汰，同时受总字节数预算约束
+struct ResponseCache {
+    entries: Mutex<LruCache<CacheKey, CachedResponse>>,
+    current_bytes: AtomicUsize,
+    max_entry_bytes: usize,
+    max_total_bytes: usize,
+}
+
+impl ResponseCache {
+    fn new(max_entry_bytes: usize, max_total_bytes: usize) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::unbounded()),
+            current_bytes: AtomicUsize::new(0),
+            max_entry_bytes,
+            max_total_bytes,
+        }
+    }
+
+    // 命中且未过期时返回缓存内容，过期条目会被顺带清理掉
+    fn get(&self, key: &CacheKey) -> Option<CachedResponse> {
+        let mut entries = self.entries.lock().ok()?;
+        let is_expired = matches!(entries.peek(key), Some(cached) if cached.expires_at <= Instant::now());
+
+        if is_expired {
+            if let Some(stale) = entries.pop(key) {
+                self.current_bytes.fetch_sub(stale.body.len(), Ordering::Relaxed);
+            }
+            return None;
+        }
+
+        entries.get(key).cloned()
+    }
+
+    // 写入缓存，超过单条大小上限则跳过；写入后按 LRU 淘汰直到低于总字节预算
+    fn put(&self, key: CacheKey, value: CachedResponse) {
+        if value.body.len() > self.max_entry_bytes {
+            return;
+        }
+
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+
+        let added = value.body.len();
+        if let Some(old) = entries.put(key, value) {
+            self.current_bytes.fetch_sub(old.body.len(), Ordering::Relaxed);
+        }
+        self.current_bytes.fetch_add(added, Ordering::Relaxed);
+
+        while self.current_bytes.load(Ordering::Relaxed) > self.max_total_bytes {
+            match entries.pop_lru() {
+                Some((_, evicted)) => {
+                    self.current_bytes.fetch_sub(evicted.body.len(), Ordering::Relaxed);
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+// 根据上游 Cache-Control/Expires 计算缓存有效期；no-store/no-cache 禁止缓存，否则退回默认 TTL
+fn compute_ttl(headers: &reqwest::header::HeaderMap, default_ttl: Duration) -> Option<Duration> {
+    if let Some(cache_control) = headers
+        .get(reqwest::header::CACHE_CONTROL)
+        .and_then(|v| v.to_str().ok())
+    {
+        let directives: Vec<&str> = cache_control.split(',').map(|d| d.trim()).collect();
+        if directives
+            .iter()
+            .any(|d| d.eq_ignore_ascii_case("no-store") || d.eq_ignore_ascii_case("no-cache"))
+        {
+            return None;
+        }
+        for directive in directives {
+            if let Some(value) = directive.strip_prefix("max-age=") {
+                if let Ok(seconds) = value.trim().parse::<u64>() {
+                    return Some(Duration::from_secs(seconds));
+                }
+            }
+        }
+    }
+
+    if let Some(expires) = headers
+        .get(reqwest::header::EXPIRES)
+        .and_then(|v| v.to_str().ok())
+    {
+        if let Ok(expires_at) = httpdate::parse_http_date(expires) {
+            return Some(
+                expires_at
+                    .duration_since(SystemTime::now())
+                    .unwrap_or(Duration::from_secs(0)),
+            );
+        }
+    }
+
+    if default_ttl.is_zero() { None } else { Some(default_ttl) }
+}
 
 // 允许转发的请求头 - 使用 HashSet 提高查找性能
 static ALLOWED_HEADERS: Lazy<std::collections::HashSet<&'static str>> = Lazy::new(|| {
@@ -81,14 +381,12 @@ static ALLOWED_HEADERS: Lazy<std::collections::HashSet<&'static str>> = Lazy::ne
     .collect()
 });
 
-// 预先生成的 HTML 内容
-static HTML_CONTENT: Lazy<String> = Lazy::new(generate_html_content);
-
 // 自定义错误类型
 #[derive(Debug)]
 enum ProxyError {
     InvalidUrl,
     ReqwestError(reqwest::Error),
+    UriTooLong,
     //HeaderError,
     //BodyTooLarge,
 }
@@ -98,6 +396,7 @@ impl std::fmt::Display for ProxyError {
         match self {
             ProxyError::InvalidUrl => write!(f, "Invalid URL"),
             ProxyError::ReqwestError(e) => write!(f, "Request error: {}", e),
+            ProxyError::UriTooLong => write!(f, "URI Too Long"),
             //ProxyError::HeaderError => write!(f, "Header processing error"),
             //ProxyError::BodyTooLarge => write!(f, "Request body too large"),
         }
@@ -119,6 +418,9 @@ impl actix_web::ResponseError for ProxyError {
             ProxyError::ReqwestError(_) => HttpResponse::BadGateway()
                 .content_type("application/json")
                 .body(r#"{"error": "Failed to process request", "code": 502}"#),
+            ProxyError::UriTooLong => HttpResponse::build(actix_web::http::StatusCode::URI_TOO_LONG)
+                .content_type("application/json")
+                .body(r#"{"error": "URI too long", "code": 414}"#),
             //ProxyError::HeaderError => HttpResponse::BadRequest()
             //    .content_type("application/json")
             //    .body(r#"{"error": "Invalid headers", "code": 400}"#),
@@ -130,8 +432,11 @@ impl actix_web::ResponseError for ProxyError {
 }
 
 // 生成 HTML 内容
-fn generate_html_content() -> String {
-    let links_html: String = API_MAPPING
+fn generate_html_content(mapping: &ApiMapping) -> String {
+    let mut entries: Vec<(&String, &Url)> = mapping.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+
+    let links_html: String = entries
         .iter()
         .map(|(path, url)| format!(r#"<li><a href="{}">{}</a> → {}</li>"#, path, path, url))
         .collect::<Vec<_>>()
@@ -230,15 +535,15 @@ fn generate_html_content() -> String {
 }
 
 // 提取路径前缀和剩余部分 - 优化性能
-fn extract_prefix_and_rest(pathname: &str) -> Option<(&'static str, &str)> {
+fn extract_prefix_and_rest<'a>(pathname: &'a str, mapping: &ApiMapping) -> Option<(String, &'a str)> {
     // 按长度降序排序，优先匹配更长的路径
-    let mut sorted_paths: Vec<&&str> = API_MAPPING.keys().collect();
-    sorted_paths.sort_by(|a, b| b.len().cmp(&a.len()));
+    let mut sorted_prefixes: Vec<&String> = mapping.keys().collect();
+    sorted_prefixes.sort_by(|a, b| b.len().cmp(&a.len()));
 
-    for &prefix in sorted_paths {
-        if pathname.starts_with(prefix) {
+    for prefix in sorted_prefixes {
+        if pathname.starts_with(prefix.as_str()) {
             let rest = &pathname[prefix.len()..];
-            return Some((prefix, rest));
+            return Some((prefix.clone(), rest));
         }
     }
     None
@@ -256,10 +561,10 @@ fn create_http_client(config: &Config) -> Client {
 }
 
 // 根路径处理器
-async fn root() -> impl Responder {
+async fn root(html_content: web::Data<String>) -> impl Responder {
     HttpResponse::Ok()
         .content_type("text/html; charset=utf-8")
-        .body(HTML_CONTENT.as_str())
+        .body(html_content.as_str().to_string())
 }
 
 // robots.txt 处理器
@@ -270,10 +575,8 @@ async fn robots() -> impl Responder {
 }
 
 // 构建目标 URL - 使用 Url::join 更安全地构建 URL
-fn build_target_url(prefix: &str, rest_path: &str) -> Result<Url, ProxyError> {
-    let base_url = API_MAPPING.get(prefix).ok_or(ProxyError::InvalidUrl)?;
-
-    let base_url = Url::parse(base_url).map_err(|_| ProxyError::InvalidUrl)?;
+fn build_target_url(prefix: &str, rest_path: &str, mapping: &ApiMapping) -> Result<Url, ProxyError> {
+    let base_url = mapping.get(prefix).ok_or(ProxyError::InvalidUrl)?;
 
     // 使用 Url::join 安全地拼接路径
     let target_url = base_url
@@ -308,10 +611,154 @@ fn process_headers(
         .collect()
 }
 
-// 处理代理响应
-async fn handle_proxy_response(response: reqwest::Response) -> Result<HttpResponse, ProxyError> {
+// 为响应添加统一的安全头
+fn apply_security_headers(builder: &mut actix_web::HttpResponseBuilder) {
+    builder
+        .insert_header(("X-Content-Type-Options", "nosniff"))
+        .insert_header(("X-Frame-Options", "DENY"))
+        .insert_header(("Referrer-Policy", "strict-origin-when-cross-origin"))
+        .insert_header(("X-XSS-Protection", "1; mode=block"));
+}
+
+// 响应体支持的压缩编码，按优先级从高到低排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ContentCoding {
+    Brotli,
+    Gzip,
+    Deflate,
+}
+
+impl ContentCoding {
+    fn as_str(self) -> &'static str {
+        match self {
+            ContentCoding::Brotli => "br",
+            ContentCoding::Gzip => "gzip",
+            ContentCoding::Deflate => "deflate",
+        }
+    }
+}
+
+// 按 brotli > gzip > deflate 的优先级，从 Accept-Encoding 中选出客户端和服务端都支持的编码
+fn negotiate_encoding(accept_encoding: &str) -> Option<ContentCoding> {
+    let accepted: Vec<&str> = accept_encoding
+        .split(',')
+        .filter_map(|part| {
+            let mut params = part.split(';');
+            let coding = params.next().unwrap_or("").trim();
+            // q=0 表示客户端明确拒绝该编码
+            let refused = params.any(|p| {
+                p.trim().strip_prefix("q=").and_then(|q| q.trim().parse::<f32>().ok()) == Some(0.0)
+            });
+            if refused { None } else { Some(coding) }
+        })
+        .collect();
+
+    [ContentCoding::Brotli, ContentCoding::Gzip, ContentCoding::Deflate]
+        .into_iter()
+        .find(|coding| accepted.iter().any(|&a| a == coding.as_str()))
+}
+
+// 判断内容类型是否适合压缩 - 排除 SSE 等流式内容以及已经是二进制/压缩格式的内容
+fn is_compressible_content_type(content_type: &str) -> bool {
+    let ct = content_type.split(';').next().unwrap_or("").trim();
+    if ct == "text/event-stream" {
+        return false;
+    }
+    ct.starts_with("text/") || ct == "application/json" || ct == "application/javascript"
+}
+
+// 流式转发场景下，响应体在访问日志记录的那一刻尚未发送完毕，此时无法得知总字节数/总耗时。
+// 该包装流在实际转发的字节流之上累计已发送字节数，并在流结束（或客户端提前断开导致被 drop）时
+// 才写入访问日志，从而记录真实的传输字节数与端到端延迟。
+struct AccessLoggedStream {
+    inner: Pin<Box<dyn Stream<Item = Result<web::Bytes, std::io::Error>> + Send>>,
+    bytes: u64,
+    logged: bool,
+    request_start: Instant,
+    logger: Arc<AccessLogger>,
+    client_ip: String,
+    method: String,
+    prefix: String,
+    target_url: String,
+    status: u16,
+}
+
+impl AccessLoggedStream {
+    fn log_now(&mut self) {
+        if self.logged {
+            return;
+        }
+        self.logged = true;
+        self.logger.log(&AccessLogRecord {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            client_ip: self.client_ip.clone(),
+            method: self.method.clone(),
+            prefix: self.prefix.clone(),
+            target_url: self.target_url.clone(),
+            status: self.status,
+            bytes: self.bytes,
+            latency_ms: self.request_start.elapsed().as_millis(),
+        });
+    }
+}
+
+impl Stream for AccessLoggedStream {
+    type Item = Result<web::Bytes, std::io::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.inner.as_mut().poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                self.bytes += chunk.len() as u64;
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Poll::Ready(Some(Err(e))) => {
+                self.log_now();
+                Poll::Ready(Some(Err(e)))
+            }
+            Poll::Ready(None) => {
+                self.log_now();
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for AccessLoggedStream {
+    // 客户端提前断开等情况下流不会正常耗尽，靠 Drop 兜底记录已传输的字节数
+    fn drop(&mut self) {
+        self.log_now();
+    }
+}
+
+// 上下文信息由 proxy_request 提供，用于在响应体实际传输完成后写入访问日志
+struct AccessLogContext {
+    logger: Arc<AccessLogger>,
+    request_start: Instant,
+    client_ip: String,
+    method: String,
+    prefix: String,
+    target_url: String,
+}
+
+// 处理代理响应 - 以流式方式转发响应体，避免在内存中整体缓冲
+// （LLM 接口常见的 SSE 长连接响应若整体缓冲会破坏增量输出，且大响应体会占用大量内存）
+async fn handle_proxy_response(
+    response: reqwest::Response,
+    accept_encoding: Option<&str>,
+    compression_enabled: bool,
+    access_log_ctx: Option<AccessLogContext>,
+) -> Result<HttpResponse, ProxyError> {
     let status = response.status();
 
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let upstream_already_encoded = response.headers().contains_key(reqwest::header::CONTENT_ENCODING);
+
     // 转换状态码
     let actix_status = actix_web::http::StatusCode::from_u16(status.as_u16())
         .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR);
@@ -319,7 +766,11 @@ async fn handle_proxy_response(response: reqwest::Response) -> Result<HttpRespon
     let mut client_resp = HttpResponse::build(actix_status);
 
     // 复制响应头 - 将 Reqwest 的 header 转换为 Actix Web 的 header
+    // Content-Length/Transfer-Encoding 交由 actix-web 根据实际流式发送情况重新计算，避免冲突
     for (name, value) in response.headers() {
+        if name == reqwest::header::CONTENT_LENGTH || name == reqwest::header::TRANSFER_ENCODING {
+            continue;
+        }
         if let (Ok(header_name), Ok(value_str)) =
             (HeaderName::from_str(name.as_str()), value.to_str())
         {
@@ -330,15 +781,89 @@ async fn handle_proxy_response(response: reqwest::Response) -> Result<HttpRespon
     }
 
     // 添加安全头
-    client_resp
-        .insert_header(("X-Content-Type-Options", "nosniff"))
-        .insert_header(("X-Frame-Options", "DENY"))
-        .insert_header(("Referrer-Policy", "strict-origin-when-cross-origin"))
-        .insert_header(("X-XSS-Protection", "1; mode=block"));
+    apply_security_headers(&mut client_resp);
+
+    // 流式转发响应体 - 将 reqwest 的错误类型映射为 actix 可接受的流错误
+    let body_stream = response
+        .bytes_stream()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e));
+
+    // 仅当上游未自行压缩、内容类型适合压缩、且客户端通过 Accept-Encoding 声明支持时才压缩
+    let negotiable = compression_enabled && !upstream_already_encoded && is_compressible_content_type(&content_type);
+    let coding = if negotiable {
+        accept_encoding.and_then(negotiate_encoding)
+    } else {
+        None
+    };
+
+    // 响应内容是否被压缩取决于客户端的 Accept-Encoding，提示缓存按该请求头分别存储
+    if negotiable {
+        client_resp.insert_header(("Vary", "Accept-Encoding"));
+    }
 
-    // 使用 bytes() 避免复制，直接返回响应体
-    let body_bytes = response.bytes().await?;
-    Ok(client_resp.body(body_bytes))
+    let forwarded_stream: Pin<Box<dyn Stream<Item = Result<web::Bytes, std::io::Error>> + Send>> = match coding {
+        Some(coding) => {
+            client_resp.insert_header(("Content-Encoding", coding.as_str()));
+            let reader = StreamReader::new(body_stream);
+            let encoded: Pin<Box<dyn AsyncRead + Send>> = match coding {
+                ContentCoding::Brotli => Box::pin(BrotliEncoder::new(reader)),
+                ContentCoding::Gzip => Box::pin(GzipEncoder::new(reader)),
+                ContentCoding::Deflate => Box::pin(DeflateEncoder::new(reader)),
+            };
+            Box::pin(ReaderStream::new(encoded))
+        }
+        None => Box::pin(body_stream),
+    };
+
+    // 有访问日志上下文时，包一层计数流，在响应体实际传输完成（或客户端提前断开）后再记录访问日志，
+    // 这样 bytes/latency_ms 反映的是真实传输结果，而不是刚收到响应头那一刻的估计值
+    match access_log_ctx {
+        Some(ctx) => Ok(client_resp.streaming(AccessLoggedStream {
+            inner: forwarded_stream,
+            bytes: 0,
+            logged: false,
+            request_start: ctx.request_start,
+            logger: ctx.logger,
+            client_ip: ctx.client_ip,
+            method: ctx.method,
+            prefix: ctx.prefix,
+            target_url: ctx.target_url,
+            status: status.as_u16(),
+        })),
+        None => Ok(client_resp.streaming(forwarded_stream)),
+    }
+}
+
+// 对已经完整读入内存的响应体按需压缩 - 供缓存命中/写入路径复用 handle_proxy_response 的压缩协商逻辑
+// 返回可能被压缩过的 body 以及实际使用的编码（未压缩时为 None）
+async fn maybe_compress_bytes(
+    body: web::Bytes,
+    content_type: &str,
+    accept_encoding: Option<&str>,
+    compression_enabled: bool,
+    already_encoded: bool,
+) -> (web::Bytes, Option<ContentCoding>) {
+    if already_encoded || !compression_enabled || !is_compressible_content_type(content_type) {
+        return (body, None);
+    }
+
+    let Some(coding) = accept_encoding.and_then(negotiate_encoding) else {
+        return (body, None);
+    };
+
+    let original = body.clone();
+    let reader = StreamReader::new(stream::once(async move { Ok::<_, std::io::Error>(body) }));
+    let mut encoded: Pin<Box<dyn AsyncRead + Send>> = match coding {
+        ContentCoding::Brotli => Box::pin(BrotliEncoder::new(reader)),
+        ContentCoding::Gzip => Box::pin(GzipEncoder::new(reader)),
+        ContentCoding::Deflate => Box::pin(DeflateEncoder::new(reader)),
+    };
+
+    let mut buf = Vec::new();
+    match encoded.read_to_end(&mut buf).await {
+        Ok(_) => (web::Bytes::from(buf), Some(coding)),
+        Err(_) => (original, None),
+    }
 }
 
 // 代理请求处理器
@@ -346,14 +871,27 @@ async fn proxy_request(
     req: HttpRequest,
     body: web::Bytes,
     client: web::Data<Client>,
+    config: web::Data<Config>,
+    mapping: web::Data<Arc<ApiMapping>>,
+    access_logger: web::Data<Option<Arc<AccessLogger>>>,
+    cache: web::Data<Option<Arc<ResponseCache>>>,
 ) -> Result<HttpResponse, ProxyError> {
+    let request_start = std::time::Instant::now();
     let path = req.path();
 
+    // 在进入映射查找前拒绝超长的路径/查询字符串，避免恶意请求拖垮上游
+    if path.len() > config.max_uri_len {
+        return Err(ProxyError::UriTooLong);
+    }
+    if req.query_string().len() > config.max_query_len {
+        return Err(ProxyError::UriTooLong);
+    }
+
     // 提取前缀和剩余路径
-    let (prefix, rest_path) = extract_prefix_and_rest(path).ok_or(ProxyError::InvalidUrl)?;
+    let (prefix, rest_path) = extract_prefix_and_rest(path, &mapping).ok_or(ProxyError::InvalidUrl)?;
 
     // 构建目标 URL - 使用 Url::join
-    let target_url = build_target_url(prefix, rest_path)?;
+    let target_url = build_target_url(&prefix, rest_path, &mapping)?;
 
     // 构建请求方法
     let method = match req.method().as_str() {
@@ -371,6 +909,108 @@ async fn proxy_request(
         }
     };
 
+    // 仅 GET/HEAD 幂等请求才会查询/写入缓存
+    let is_cacheable_method = method == Method::GET || method == Method::HEAD;
+    let cache_key = match (cache.as_ref(), is_cacheable_method) {
+        (Some(_), true) => Some(CacheKey {
+            method: method.to_string(),
+            url: target_url.to_string(),
+            accept: req
+                .headers()
+                .get(actix_web::http::header::ACCEPT)
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string()),
+            authorization: req
+                .headers()
+                .get(actix_web::http::header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string()),
+            api_key: req
+                .headers()
+                .get("x-api-key")
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string()),
+            goog_api_key: req
+                .headers()
+                .get("x-goog-api-key")
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string()),
+        }),
+        _ => None,
+    };
+
+    let accept_encoding = req
+        .headers()
+        .get(actix_web::http::header::ACCEPT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+
+    if let (Some(responses), Some(key)) = (cache.as_ref(), &cache_key) {
+        if let Some(cached) = responses.get(key) {
+            if let Some(logger) = access_logger.as_ref() {
+                logger.log(&AccessLogRecord {
+                    timestamp: chrono::Utc::now().to_rfc3339(),
+                    client_ip: req
+                        .connection_info()
+                        .realip_remote_addr()
+                        .unwrap_or("unknown")
+                        .to_string(),
+                    method: req.method().to_string(),
+                    prefix: prefix.clone(),
+                    target_url: target_url.to_string(),
+                    status: cached.status,
+                    bytes: cached.body.len() as u64,
+                    latency_ms: request_start.elapsed().as_millis(),
+                });
+            }
+
+            // 缓存中存的是上游返回的原始字节（代理自身不压缩），按本次请求的 Accept-Encoding 重新协商压缩，
+            // 这样不同客户端命中同一缓存条目也能各自拿到合适的编码
+            let cached_content_type = cached
+                .headers
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(reqwest::header::CONTENT_TYPE.as_str()))
+                .map(|(_, value)| value.as_str())
+                .unwrap_or("");
+            let already_encoded = cached
+                .headers
+                .iter()
+                .any(|(name, _)| name.eq_ignore_ascii_case(reqwest::header::CONTENT_ENCODING.as_str()));
+            // 与 handle_proxy_response 的 negotiable 判定保持一致，避免对不可压缩内容或
+            // --compression=false 时仍误加 Vary: Accept-Encoding
+            let negotiable = config.compression && !already_encoded && is_compressible_content_type(cached_content_type);
+            let (body, coding) = maybe_compress_bytes(
+                cached.body.clone(),
+                cached_content_type,
+                accept_encoding.as_deref(),
+                config.compression,
+                already_encoded,
+            )
+            .await;
+
+            let mut builder = HttpResponse::build(
+                actix_web::http::StatusCode::from_u16(cached.status)
+                    .unwrap_or(actix_web::http::StatusCode::OK),
+            );
+            for (name, value) in &cached.headers {
+                if let (Ok(header_name), Ok(header_value)) =
+                    (HeaderName::from_str(name), HeaderValue::from_str(value))
+                {
+                    builder.insert_header((header_name, header_value));
+                }
+            }
+            apply_security_headers(&mut builder);
+            if let Some(coding) = coding {
+                builder.insert_header(("Content-Encoding", coding.as_str()));
+            }
+            if negotiable {
+                builder.insert_header(("Vary", "Accept-Encoding"));
+            }
+            builder.insert_header(("X-Proxy-Cache", "HIT"));
+            return Ok(builder.body(body));
+        }
+    }
+
     // 处理请求头
     let headers = process_headers(&req);
 
@@ -383,7 +1023,112 @@ async fn proxy_request(
 
     // 使用 body 的引用避免复制
     let response = request_builder.body(body).send().await?;
-    handle_proxy_response(response).await
+
+    let client_ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .unwrap_or("unknown")
+        .to_string();
+    let request_method = req.method().to_string();
+
+    // 可缓存时整体缓冲响应体以便写入缓存，并打上 X-Proxy-Cache: MISS
+    if let (Some(responses), Some(key)) = (cache.as_ref(), cache_key) {
+        if response.status().is_success() {
+            if let Some(ttl) = compute_ttl(response.headers(), Duration::from_secs(config.cache_ttl))
+                .filter(|ttl| !ttl.is_zero())
+            {
+                let stored_headers: Vec<(String, String)> = response
+                    .headers()
+                    .iter()
+                    .filter(|(name, _)| {
+                        let name = name.as_str();
+                        name != reqwest::header::CONTENT_LENGTH.as_str()
+                            && name != reqwest::header::TRANSFER_ENCODING.as_str()
+                    })
+                    .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+                    .collect();
+                let status = response.status().as_u16();
+                let content_type = response
+                    .headers()
+                    .get(reqwest::header::CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or("")
+                    .to_string();
+                let already_encoded = response.headers().contains_key(reqwest::header::CONTENT_ENCODING);
+                // 与 handle_proxy_response 的 negotiable 判定保持一致，避免对不可压缩内容或
+                // --compression=false 时仍误加 Vary: Accept-Encoding
+                let negotiable = config.compression && !already_encoded && is_compressible_content_type(&content_type);
+                let body_bytes = response.bytes().await?;
+
+                // 响应体已整体缓冲，字节数和延迟此刻都是确定的，可以直接记录访问日志
+                if let Some(logger) = access_logger.as_ref() {
+                    logger.log(&AccessLogRecord {
+                        timestamp: chrono::Utc::now().to_rfc3339(),
+                        client_ip: client_ip.clone(),
+                        method: request_method.clone(),
+                        prefix: prefix.clone(),
+                        target_url: target_url.to_string(),
+                        status,
+                        bytes: body_bytes.len() as u64,
+                        latency_ms: request_start.elapsed().as_millis(),
+                    });
+                }
+
+                // 缓存中始终存上游返回的原始字节（代理自身不压缩），压缩按每次请求的 Accept-Encoding 即时协商
+                responses.put(
+                    key,
+                    CachedResponse {
+                        status,
+                        headers: stored_headers.clone(),
+                        body: body_bytes.clone(),
+                        expires_at: Instant::now() + ttl,
+                    },
+                );
+
+                let (body, coding) = maybe_compress_bytes(
+                    body_bytes,
+                    &content_type,
+                    accept_encoding.as_deref(),
+                    config.compression,
+                    already_encoded,
+                )
+                .await;
+
+                let mut builder = HttpResponse::build(
+                    actix_web::http::StatusCode::from_u16(status)
+                        .unwrap_or(actix_web::http::StatusCode::OK),
+                );
+                for (name, value) in &stored_headers {
+                    if let (Ok(header_name), Ok(header_value)) =
+                        (HeaderName::from_str(name), HeaderValue::from_str(value))
+                    {
+                        builder.insert_header((header_name, header_value));
+                    }
+                }
+                apply_security_headers(&mut builder);
+                if let Some(coding) = coding {
+                    builder.insert_header(("Content-Encoding", coding.as_str()));
+                }
+                if negotiable {
+                    builder.insert_header(("Vary", "Accept-Encoding"));
+                }
+                builder.insert_header(("X-Proxy-Cache", "MISS"));
+                return Ok(builder.body(body));
+            }
+        }
+    }
+
+    // 流式转发路径：把访问日志的记录时机延后到响应体实际传输完成（详见 AccessLoggedStream）
+    let access_log_ctx = access_logger.as_ref().cloned().map(|logger| AccessLogContext {
+        logger,
+        request_start,
+        client_ip,
+        method: request_method,
+        prefix: prefix.clone(),
+        target_url: target_url.to_string(),
+    });
+
+    handle_proxy_response(response, accept_encoding.as_deref(), config.compression, access_log_ctx).await
 }
 
 // 健康检查端点
@@ -404,26 +1149,76 @@ async fn main() -> std::io::Result<()> {
     }
     env_logger::init();
 
-    println!(
-        "🚀 Starting API Proxy Server on {}:{}",
-        config.host, config.port
-    );
+    if config.disable_tcp && config.uds.is_none() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--disable-tcp requires --uds to be set",
+        ));
+    }
+
+    if config.disable_tcp {
+        println!("🚀 Starting API Proxy Server (Unix domain socket only)");
+    } else {
+        println!(
+            "🚀 Starting API Proxy Server on {}:{}",
+            config.host, config.port
+        );
+    }
     println!("📊 Configuration:");
     println!("   Workers: {}", config.workers);
     println!("   Max Body Size: {}MB", config.max_body_size_mb);
     println!("   Request Timeout: {}s", config.request_timeout);
     println!("   Connect Timeout: {}s", config.connect_timeout);
+    println!("   Compression: {}", config.compression);
+    if let Some(uds) = &config.uds {
+        println!("   Unix Socket: {}", uds);
+    }
+
+    let api_mapping = Arc::new(load_api_mapping(&config)?);
     println!("📊 Available endpoints:");
-    for (path, url) in API_MAPPING.iter() {
+    for (path, url) in api_mapping.iter() {
         println!("   {} -> {}", path, url);
     }
 
     let client = create_http_client(&config);
     let max_body_size = config.max_body_size_mb * 1024 * 1024; // 转换为字节
+    let html_content = generate_html_content(&api_mapping);
+    let host = config.host.clone();
+    let port = config.port;
+    let workers = config.workers;
+    let uds_path = config.uds.clone();
+    let uds_perms = config.uds_perms.clone();
+    let disable_tcp = config.disable_tcp;
 
-    let server = HttpServer::new(move || {
+    let access_logger: Option<Arc<AccessLogger>> = match config.access_log.as_deref() {
+        Some(path) => {
+            println!("   Access Log: {} (rotate at {} bytes)", path, config.access_log_max_bytes);
+            Some(Arc::new(AccessLogger::new(path, config.access_log_max_bytes)?))
+        }
+        None => None,
+    };
+
+    let cache: Option<Arc<ResponseCache>> = if config.cache_ttl > 0 {
+        println!(
+            "   Response Cache: ttl={}s max_entry={}B max_total={}B",
+            config.cache_ttl, config.cache_max_entry_bytes, config.cache_max_total_bytes
+        );
+        Some(Arc::new(ResponseCache::new(
+            config.cache_max_entry_bytes,
+            config.cache_max_total_bytes,
+        )))
+    } else {
+        None
+    };
+
+    let mut server = HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(client.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .app_data(web::Data::new(api_mapping.clone()))
+            .app_data(web::Data::new(html_content.clone()))
+            .app_data(web::Data::new(access_logger.clone()))
+            .app_data(web::Data::new(cache.clone()))
             // 配置请求体大小限制
             .app_data(web::PayloadConfig::new(max_body_size))
             .route("/", web::get().to(root))
@@ -432,14 +1227,90 @@ async fn main() -> std::io::Result<()> {
             .route("/health", web::get().to(health_check))
             .default_service(web::route().to(proxy_request))
     })
-    .bind((config.host.as_str(), config.port))?
-    .workers(config.workers)
+    .workers(workers)
     .backlog(1024)
     .max_connection_rate(1000);
 
-    println!(
-        "✅ Server running at http://{}:{}",
-        config.host, config.port
-    );
+    // 仅在未禁用 TCP 时绑定 host:port，让 --disable-tcp 搭配 --uds 实现纯 UDS 监听
+    if !disable_tcp {
+        server = server.bind((host.as_str(), port))?;
+    }
+
+    // 如果配置了 Unix 域套接字路径，额外在该路径上监听
+    if let Some(path) = uds_path.as_deref() {
+        // 清理上一次运行遗留的套接字文件，避免 "address already in use"
+        if std::path::Path::new(path).exists() {
+            std::fs::remove_file(path)?;
+        }
+
+        server = server.bind_uds(path)?;
+
+        let perms_mode = u32::from_str_radix(&uds_perms, 8).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Invalid --uds-perms value: {}", uds_perms),
+            )
+        })?;
+        std::fs::set_permissions(
+            path,
+            std::os::unix::fs::PermissionsExt::from_mode(perms_mode),
+        )?;
+
+        println!("🔌 Also listening on Unix domain socket at {}", path);
+    }
+
+    if !disable_tcp {
+        println!("✅ Server running at http://{}:{}", host, port);
+    }
     server.run().await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache_key(method: &str, url: &str, api_key: Option<&str>) -> CacheKey {
+        CacheKey {
+            method: method.to_string(),
+            url: url.to_string(),
+            accept: None,
+            authorization: None,
+            api_key: api_key.map(|s| s.to_string()),
+            goog_api_key: None,
+        }
+    }
+
+    // 回归测试：两个客户端用不同的 x-api-key 请求同一个可缓存 URL，
+    // 绝不能让后来者读到前一个客户端的缓存响应（跨密钥数据泄露）
+    #[test]
+    fn cache_does_not_leak_across_different_api_keys() {
+        let cache = ResponseCache::new(1024, 1024 * 1024);
+        let key_a = cache_key("GET", "https://api.example.com/v1/models", Some("key-a"));
+        let key_b = cache_key("GET", "https://api.example.com/v1/models", Some("key-b"));
+
+        assert_ne!(key_a, key_b, "distinct x-api-key values must not collide in the cache key");
+
+        cache.put(
+            key_a.clone(),
+            CachedResponse {
+                status: 200,
+                headers: vec![],
+                body: web::Bytes::from_static(b"models for key-a"),
+                expires_at: Instant::now() + Duration::from_secs(60),
+            },
+        );
+
+        assert!(cache.get(&key_a).is_some());
+        assert!(
+            cache.get(&key_b).is_none(),
+            "a request authenticated with key-b must not be served key-a's cached response"
+        );
+    }
+
+    #[test]
+    fn cache_key_identical_for_repeated_identical_requests() {
+        let key_1 = cache_key("GET", "https://api.example.com/v1/models", Some("key-a"));
+        let key_2 = cache_key("GET", "https://api.example.com/v1/models", Some("key-a"));
+        assert_eq!(key_1, key_2);
+    }
+}